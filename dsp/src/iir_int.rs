@@ -8,31 +8,235 @@ use serde::{Deserialize, Serialize};
 pub struct Vec5(pub [i32; 5]);
 
 impl Vec5 {
+    /// `(cos, sin)` of `2*pi*f`.
+    ///
+    /// Uses a 3rd order Taylor approximation, accurate for corner frequencies
+    /// `f` below about 1% of the sample rate, and falls back to `f32::sin`/`cos`
+    /// above that, where the Taylor approximation diverges.
+    fn cos_sin(f: f32) -> (f32, f32) {
+        let w = f * 2. * PI;
+        if f.abs() < 0.01 {
+            let w2 = w * w * 0.5;
+            (1. - w2, w * (1. - w2 / 3.))
+        } else {
+            (w.cos(), w.sin())
+        }
+    }
+
+    /// Normalize cookbook `[b0,b1,b2,a0,a1,a2]` biquad coefficients into the
+    /// `[b0,b1,b2,a1,a2]` Q2.30 fixed-point form used by `IIR` (`a0` is
+    /// implicitly -1).
+    fn to_ba(b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) -> Self {
+        // IIR uses Q2.30 fixed point
+        let a0 = a0 / (1 << IIR::SHIFT) as f32;
+        Self([
+            (b0 / a0) as _,
+            (b1 / a0) as _,
+            (b2 / a0) as _,
+            (-a1 / a0) as _,
+            (-a2 / a0) as _,
+        ])
+    }
+
     /// Lowpass biquad filter using cutoff and sampling frequencies.  Taken from:
     /// https://webaudio.github.io/Audio-EQ-Cookbook/audio-eq-cookbook.html
     ///
     /// # Args
     /// * `f` - Corner frequency, or 3dB cutoff frequency (in units of sample rate).
-    ///         This is only accurate for low corner frequencies less than ~0.01.
+    ///         `cos_sin`'s Taylor approximation is used below about 1% of the
+    ///         sample rate; above that it falls back to `f32::sin`/`cos`.
     /// * `q` - Quality factor (1/sqrt(2) for critical).
     /// * `k` - DC gain.
     ///
     /// # Returns
     /// 2nd-order IIR filter coefficients in the form [b0,b1,b2,a1,a2]. a0 is set to -1.
     pub fn lowpass(f: f32, q: f32, k: f32) -> Self {
-        // 3rd order Taylor approximation of sin and cos.
-        let f = f * 2. * PI;
-        let f2 = f * f * 0.5;
-        let fcos = 1. - f2;
-        let fsin = f * (1. - f2 / 3.);
-        let alpha = fsin / (2. * q);
-        // IIR uses Q2.30 fixed point
-        let a0 = (1. + alpha) / (1 << IIR::SHIFT) as f32;
-        let b0 = (k / 2. * (1. - fcos) / a0) as _;
-        let a1 = (2. * fcos / a0) as _;
-        let a2 = ((alpha - 1.) / a0) as _;
+        let (cosw, sinw) = Self::cos_sin(f);
+        let alpha = sinw / (2. * q);
+        Self::to_ba(
+            k / 2. * (1. - cosw),
+            k * (1. - cosw),
+            k / 2. * (1. - cosw),
+            1. + alpha,
+            -2. * cosw,
+            1. - alpha,
+        )
+    }
+
+    /// Highpass biquad filter. See [`Vec5::lowpass`] for details.
+    ///
+    /// # Args
+    /// * `f` - Corner frequency, in units of sample rate.
+    /// * `q` - Quality factor (1/sqrt(2) for critical).
+    /// * `k` - Gain at Nyquist.
+    pub fn highpass(f: f32, q: f32, k: f32) -> Self {
+        let (cosw, sinw) = Self::cos_sin(f);
+        let alpha = sinw / (2. * q);
+        Self::to_ba(
+            k / 2. * (1. + cosw),
+            -k * (1. + cosw),
+            k / 2. * (1. + cosw),
+            1. + alpha,
+            -2. * cosw,
+            1. - alpha,
+        )
+    }
+
+    /// Bandpass biquad filter with constant skirt gain (peak gain is `k*q`).
+    /// See [`Vec5::lowpass`] for details.
+    ///
+    /// # Args
+    /// * `f` - Center frequency, in units of sample rate.
+    /// * `q` - Quality factor.
+    /// * `k` - Skirt gain.
+    pub fn bandpass(f: f32, q: f32, k: f32) -> Self {
+        let (cosw, sinw) = Self::cos_sin(f);
+        let alpha = sinw / (2. * q);
+        Self::to_ba(
+            k * sinw / 2.,
+            0.,
+            -k * sinw / 2.,
+            1. + alpha,
+            -2. * cosw,
+            1. - alpha,
+        )
+    }
+
+    /// Bandpass biquad filter with constant peak gain `k` (0 dB for `k = 1`).
+    /// See [`Vec5::lowpass`] for details.
+    ///
+    /// # Args
+    /// * `f` - Center frequency, in units of sample rate.
+    /// * `q` - Quality factor.
+    /// * `k` - Peak gain.
+    pub fn bandpass_peak(f: f32, q: f32, k: f32) -> Self {
+        let (cosw, sinw) = Self::cos_sin(f);
+        let alpha = sinw / (2. * q);
+        Self::to_ba(
+            k * alpha,
+            0.,
+            -k * alpha,
+            1. + alpha,
+            -2. * cosw,
+            1. - alpha,
+        )
+    }
+
+    /// Notch biquad filter. See [`Vec5::lowpass`] for details.
+    ///
+    /// # Args
+    /// * `f` - Notch frequency, in units of sample rate.
+    /// * `q` - Quality factor.
+    /// * `k` - Gain away from the notch.
+    pub fn notch(f: f32, q: f32, k: f32) -> Self {
+        let (cosw, sinw) = Self::cos_sin(f);
+        let alpha = sinw / (2. * q);
+        Self::to_ba(k, -2. * k * cosw, k, 1. + alpha, -2. * cosw, 1. - alpha)
+    }
+
+    /// Allpass biquad filter. See [`Vec5::lowpass`] for details.
+    ///
+    /// # Args
+    /// * `f` - Center frequency, in units of sample rate.
+    /// * `q` - Quality factor.
+    /// * `k` - Gain.
+    pub fn allpass(f: f32, q: f32, k: f32) -> Self {
+        let (cosw, sinw) = Self::cos_sin(f);
+        let alpha = sinw / (2. * q);
+        Self::to_ba(
+            k * (1. - alpha),
+            -2. * k * cosw,
+            k * (1. + alpha),
+            1. + alpha,
+            -2. * cosw,
+            1. - alpha,
+        )
+    }
+
+    /// Peaking EQ biquad filter. See [`Vec5::lowpass`] for details.
+    ///
+    /// # Args
+    /// * `f` - Center frequency, in units of sample rate.
+    /// * `q` - Quality factor (bandwidth of the peak).
+    /// * `k` - Peak gain.
+    pub fn peaking(f: f32, q: f32, k: f32) -> Self {
+        let (cosw, sinw) = Self::cos_sin(f);
+        let alpha = sinw / (2. * q);
+        let a = k.sqrt();
+        Self::to_ba(
+            1. + alpha * a,
+            -2. * cosw,
+            1. - alpha * a,
+            1. + alpha / a,
+            -2. * cosw,
+            1. - alpha / a,
+        )
+    }
+
+    /// Low shelf biquad filter. See [`Vec5::lowpass`] for details.
+    ///
+    /// # Args
+    /// * `f` - Shelf midpoint frequency, in units of sample rate.
+    /// * `q` - Shelf slope quality factor (1 for one octave).
+    /// * `k` - Gain below the shelf.
+    pub fn lowshelf(f: f32, q: f32, k: f32) -> Self {
+        let (cosw, sinw) = Self::cos_sin(f);
+        let alpha = sinw / (2. * q);
+        let a = k.sqrt();
+        let two_sqrt_a_alpha = 2. * a.sqrt() * alpha;
+        Self::to_ba(
+            a * (a + 1. - (a - 1.) * cosw + two_sqrt_a_alpha),
+            2. * a * (a - 1. - (a + 1.) * cosw),
+            a * (a + 1. - (a - 1.) * cosw - two_sqrt_a_alpha),
+            a + 1. + (a - 1.) * cosw + two_sqrt_a_alpha,
+            -2. * (a - 1. + (a + 1.) * cosw),
+            a + 1. + (a - 1.) * cosw - two_sqrt_a_alpha,
+        )
+    }
+
+    /// High shelf biquad filter. See [`Vec5::lowpass`] for details.
+    ///
+    /// # Args
+    /// * `f` - Shelf midpoint frequency, in units of sample rate.
+    /// * `q` - Shelf slope quality factor (1 for one octave).
+    /// * `k` - Gain above the shelf.
+    pub fn highshelf(f: f32, q: f32, k: f32) -> Self {
+        let (cosw, sinw) = Self::cos_sin(f);
+        let alpha = sinw / (2. * q);
+        let a = k.sqrt();
+        let two_sqrt_a_alpha = 2. * a.sqrt() * alpha;
+        Self::to_ba(
+            a * (a + 1. + (a - 1.) * cosw + two_sqrt_a_alpha),
+            -2. * a * (a - 1. + (a + 1.) * cosw),
+            a * (a + 1. + (a - 1.) * cosw - two_sqrt_a_alpha),
+            a + 1. - (a - 1.) * cosw + two_sqrt_a_alpha,
+            2. * (a - 1. - (a + 1.) * cosw),
+            a + 1. - (a - 1.) * cosw - two_sqrt_a_alpha,
+        )
+    }
+
+    /// PI controller biquad, discretized by backward-Euler (Clegg) integration.
+    ///
+    /// # Args
+    /// * `kp` - Proportional gain.
+    /// * `ki` - Integral gain (continuous, per second).
+    /// * `f_sample` - Sampling frequency.
+    pub fn pi(kp: f32, ki: f32, f_sample: f32) -> Self {
+        Self::pid(kp, ki, 0., f_sample)
+    }
 
-        Self([b0, 2 * b0, b0, a1, a2])
+    /// PID controller biquad, discretized by backward-Euler integration and
+    /// backward-difference differentiation.
+    ///
+    /// # Args
+    /// * `kp` - Proportional gain.
+    /// * `ki` - Integral gain (continuous, per second).
+    /// * `kd` - Derivative gain (continuous, in seconds).
+    /// * `f_sample` - Sampling frequency.
+    pub fn pid(kp: f32, ki: f32, kd: f32, f_sample: f32) -> Self {
+        let ts = 1. / f_sample;
+        let td = kd / ts;
+        Self::to_ba(kp + ki * ts + td, -kp - 2. * td, td, 1., -1., 0.)
     }
 }
 
@@ -50,14 +254,27 @@ fn macc(y0: i32, x: &[i32], a: &[i32], shift: u32) -> i32 {
 /// Integer biquad IIR
 ///
 /// See `dsp::iir::IIR` for general implementation details.
-/// Offset and limiting disabled to suit lowpass applications.
 /// Coefficient scaling fixed and optimized.
-#[derive(Copy, Clone, Default, Deserialize, Serialize)]
+#[derive(Copy, Clone, Deserialize, Serialize)]
 pub struct IIR {
     pub ba: Vec5,
-    // pub y_offset: i32,
-    // pub y_min: i32,
-    // pub y_max: i32,
+    pub y_offset: i32,
+    pub y_min: i32,
+    pub y_max: i32,
+}
+
+impl Default for IIR {
+    /// An unconfigured `IIR` is unity-gain pass-through, not muted: the derived
+    /// `Default` would leave `y_min`/`y_max` at `0`, clamping every output to `0`
+    /// regardless of `ba`.
+    fn default() -> Self {
+        Self {
+            ba: Vec5([1 << IIR::SHIFT, 0, 0, 0, 0]),
+            y_offset: 0,
+            y_min: i32::MIN,
+            y_max: i32::MAX,
+        }
+    }
 }
 
 impl IIR {
@@ -81,23 +298,175 @@ impl IIR {
         xy.0.copy_within(0..n - 1, 1);
         // Store x0            x0 x1 x2 y1 y2
         xy.0[0] = x0;
-        // Compute y0 by multiply-accumulate
-        let y0 = macc(0, &xy.0, &self.ba.0, IIR::SHIFT);
-        // Limit y0
-        // let y0 = y0.max(self.y_min).min(self.y_max);
+        // Compute y0 by multiply-accumulate, seeded with the offset (scaled
+        // into the same Q2.30 fixed point as the coefficients so it
+        // participates in the rounded `macc`).
+        let y0 = macc(self.y_offset, &xy.0, &self.ba.0, IIR::SHIFT);
+        // Limit y0. The clamped value (and not the unclamped accumulator
+        // output) is what gets fed back through the `a1`/`a2` taps below, so
+        // a saturated integrator stops winding up (Clegg-style anti-windup).
+        let y0 = y0.max(self.y_min).min(self.y_max);
         // Store y0            x0 x1 y0 y1 y2
         xy.0[n / 2] = y0;
         y0
     }
 }
 
+/// Cascade of `N` biquad `IIR` sections, for higher (even) order filters.
+///
+/// Each section's output feeds the next section's input. State (`Vec5` per
+/// section) is laid out contiguously in an array so the per-section
+/// `copy_within` + `macc` sequence in `IIR::update` still compiles to the
+/// same unrolled code, just repeated `N` times.
+///
+/// Building the `N` sections themselves (e.g. factoring a designed Nth-order
+/// transfer function into normalized second-order sections by pole/zero
+/// pairing) is out of scope here: it needs a polynomial root finder, which
+/// this crate deliberately has none of — every other filter here (see
+/// [`Vec5::lowpass`] and friends) is a closed-form cookbook biquad. Build a
+/// cascade from a table of such biquads (designed by hand, or by an external
+/// filter design tool) and use [`IirCascade::balance_gains`] to spread their
+/// gain for headroom.
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub struct IirCascade<const N: usize> {
+    pub iir: [IIR; N],
+}
+
+impl<const N: usize> Default for IirCascade<N> {
+    fn default() -> Self {
+        Self {
+            iir: [IIR::default(); N],
+        }
+    }
+}
+
+impl<const N: usize> IirCascade<N> {
+    /// Feed a new input value through all `N` sections in order, updating
+    /// each section's state, and return the cascade's output.
+    ///
+    /// # Arguments
+    /// * `xy` - Current filter state, one `Vec5` per section.
+    /// * `x0` - New input.
+    pub fn update(&self, xy: &mut [Vec5; N], x0: i32) -> i32 {
+        self.iir
+            .iter()
+            .zip(xy.iter_mut())
+            .fold(x0, |x, (iir, xy)| iir.update(xy, x))
+    }
+
+    /// Peak gain of a single section's coefficients, estimated as the largest
+    /// `|H(e^jw)|` over a grid of frequencies spanning `[0, pi)`.
+    ///
+    /// Unlike DC (`z = 1`) gain, this is nonzero for bandpass/highpass/notch
+    /// sections too (DC gain is exactly zero for a bandpass or notch, and
+    /// numerically ~0 for a highpass), so it works as a uniform headroom
+    /// metric regardless of the kind of section `balance_gains` is given.
+    fn peak_gain(ba: &Vec5) -> f32 {
+        const STEPS: usize = 64;
+        let shift = (1i64 << IIR::SHIFT) as f32;
+        let [b0, b1, b2, a1, a2] = ba.0;
+        let (b0, b1, b2) = (b0 as f32 / shift, b1 as f32 / shift, b2 as f32 / shift);
+        let (a1, a2) = (a1 as f32 / shift, a2 as f32 / shift);
+        (0..STEPS)
+            .map(|i| {
+                let w = PI * i as f32 / STEPS as f32;
+                let (cosw, sinw) = (w.cos(), w.sin());
+                let (cos2w, sin2w) = ((2. * w).cos(), (2. * w).sin());
+                let num_re = b0 + b1 * cosw + b2 * cos2w;
+                let num_im = -b1 * sinw - b2 * sin2w;
+                let den_re = 1. - a1 * cosw - a2 * cos2w;
+                let den_im = a1 * sinw + a2 * sin2w;
+                ((num_re * num_re + num_im * num_im)
+                    / (den_re * den_re + den_im * den_im))
+                    .sqrt()
+            })
+            .fold(0f32, f32::max)
+    }
+
+    /// Redistribute gain evenly across all `N` sections by rescaling each
+    /// section's `b` taps so that its peak gain equals the geometric mean of
+    /// the sections' peak gains.
+    ///
+    /// Designing a higher-order filter as SOS and concentrating most of the
+    /// gain in one section risks overflowing that section's Q2.30 `macc`
+    /// accumulator while the others sit far below full scale. Spreading the
+    /// gain geometrically across sections keeps every section's headroom
+    /// comparable without changing the cascade's overall transfer function.
+    ///
+    /// This only rebalances gain across sections that the caller has already
+    /// split into a cascade (e.g. by hand, or from a table of cookbook
+    /// biquads); it does not decompose a single higher-order transfer
+    /// function into those sections itself.
+    pub fn balance_gains(&mut self) {
+        let total: f32 = self.iir.iter().map(|iir| Self::peak_gain(&iir.ba)).product();
+        let target = total.powf(1. / N as f32);
+        for iir in self.iir.iter_mut() {
+            let gain = Self::peak_gain(&iir.ba);
+            if gain == 0. {
+                continue;
+            }
+            let scale = target / gain;
+            for b in &mut iir.ba.0[0..3] {
+                *b = (*b as f32 * scale) as i32;
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::Vec5;
+    use super::{IirCascade, Vec5, IIR};
 
     #[test]
     fn lowpass_gen() {
         let ba = Vec5::lowpass(1e-3, 1. / 2f32.sqrt(), 2.);
         println!("{:?}", ba.0);
     }
+
+    #[test]
+    fn default_is_pass_through() {
+        let iir = IIR::default();
+        let mut xy = Vec5::default();
+        assert_eq!(iir.update(&mut xy, 12345), 12345);
+    }
+
+    #[test]
+    fn cascade_default_is_not_mute() {
+        let cascade = IirCascade::<3>::default();
+        let mut xy = [Vec5::default(); 3];
+        assert_eq!(cascade.update(&mut xy, 12345), 12345);
+    }
+
+    #[test]
+    fn balance_gains_survives_zero_dc_gain_sections() {
+        // A bandpass section has exactly zero DC gain, and a highpass section
+        // has DC gain numerically indistinguishable from zero; a DC-gain-based
+        // metric would blow up `scale` for these (or zero out the whole
+        // cascade once any section's DC gain is exactly zero).
+        let mut cascade = IirCascade::<2> {
+            iir: [
+                IIR {
+                    ba: Vec5::bandpass(0.1, 5., 1.0),
+                    ..IIR::default()
+                },
+                IIR {
+                    ba: Vec5::highpass(0.2, 1. / 2f32.sqrt(), 1.0),
+                    ..IIR::default()
+                },
+            ],
+        };
+        cascade.balance_gains();
+        for iir in &cascade.iir {
+            // A DC-gain-based `scale` would be `inf`/huge here, saturating every
+            // `b` tap to `i32::MAX`/`MIN` (or, via the zeroed `total` product,
+            // to exactly 0). Peak-gain balancing keeps both sections' taps
+            // finite and nonzero.
+            assert!(iir.ba.0[0..3].iter().any(|&b| b != 0));
+            assert!(iir
+                .ba
+                .0
+                .iter()
+                .all(|&b| b != i32::MAX && b != i32::MIN));
+        }
+    }
 }
@@ -0,0 +1,119 @@
+use num_complex::Complex;
+
+use super::iir_int::{Vec5, IIR};
+
+/// Fast, fixed point `(cos, sin)` of a wrapping phase.
+///
+/// Uses half-wave symmetry (sine is evaluated over `[0, pi)` and negated for the other
+/// half; cosine is the sine a quarter turn ahead) together with Bhaskara I's rational
+/// sine approximation, which
+/// is exact at the quadrant boundaries and within about 0.2% elsewhere. Everything is
+/// wrapping fixed point (`i32`/`i64`/`i128` intermediates): there is no floating point on
+/// this hot path.
+///
+/// # Returns
+/// `(cos, sin)` as signed Q31 fractions of full scale (`i32::MAX` represents `1.0`).
+pub fn cossin(phase: i32) -> (i32, i32) {
+    const ONE: i64 = 1 << 31;
+
+    // Bhaskara I's sine approximation: sin(pi*y) = 16*y*(1-y) / (5 - 4*y*(1-y))
+    // for y in [0, 1]. Half-wave symmetry below only ever calls this with y
+    // in [0, 1), reducing sin of any phase to one evaluation.
+    fn bhaskara(y: i64) -> i64 {
+        let y = y as i128;
+        let one = ONE as i128;
+        let z = (y * (one - y)) >> 31; // Q31, y*(1-y), in [0, 0.25]
+        let num = 16 * z; // Q31, in [0, 4.0]
+        let den = 5 * one - 4 * z; // Q31, in [4.0, 5.0]
+        ((num * one) / den) as i64 // Q31, in [0, 1.0]
+    }
+
+    fn sin(phase: i32) -> i32 {
+        let bits = phase as u32;
+        let quadrant = bits >> 30;
+        let rem = (bits & 0x3fff_ffff) as i64;
+        // y = phase/pi, folded into [0, 1) by quadrant.
+        let y = if quadrant % 2 == 0 {
+            rem
+        } else {
+            (1i64 << 30) + rem
+        };
+        // Saturate: y = 0.5 maps to exactly 1.0, which has no signed Q31 representation.
+        let magnitude = bhaskara(y).min(i32::MAX as i64) as i32;
+        if quadrant >= 2 {
+            -magnitude
+        } else {
+            magnitude
+        }
+    }
+
+    (sin(phase.wrapping_add(1 << 30)), sin(phase))
+}
+
+/// Lock-in amplifier: demodulates a raw ADC sample against a reference phase.
+///
+/// Combines [`cossin`] with two independent [`IIR`] low-pass filters to recover the
+/// baseband in-phase/quadrature (`I`/`Q`) amplitude of the component of `x0` at the
+/// reference frequency, rejecting everything else.
+#[derive(Copy, Clone, Default)]
+pub struct Lockin {
+    iir: IIR,
+}
+
+impl Lockin {
+    /// Construct a lock-in using `iir` as the (identical) low-pass for both the I and Q
+    /// channels.
+    pub fn new(iir: IIR) -> Self {
+        Self { iir }
+    }
+
+    /// Demodulate one sample.
+    ///
+    /// # Args
+    /// * `state` - Low-pass filter state, one per channel (`[I, Q]`). Only this is
+    ///   modified.
+    /// * `x0` - Raw ADC sample.
+    /// * `phase` - Reference phase, e.g. from `PLLState::update`.
+    pub fn update(&self, state: &mut [Vec5; 2], x0: i32, phase: i32) -> Complex<i32> {
+        let (cos, sin) = cossin(phase);
+        let i = ((x0 as i64 * cos as i64) >> 31) as i32;
+        let q = ((x0 as i64 * sin as i64) >> 31) as i32;
+        let i = self.iir.update(&mut state[0], i);
+        let q = self.iir.update(&mut state[1], q);
+        Complex::new(i, q)
+    }
+
+    /// Demodulate one sample against the `n`-th harmonic of `phase`.
+    pub fn update_harmonic(
+        &self,
+        state: &mut [Vec5; 2],
+        x0: i32,
+        phase: i32,
+        n: i32,
+    ) -> Complex<i32> {
+        self.update(state, x0, phase.wrapping_mul(n))
+    }
+}
+
+/// Magnitude of a demodulated `I`/`Q` sample.
+pub fn magnitude(iq: Complex<i32>) -> f32 {
+    ((iq.re as i64 * iq.re as i64 + iq.im as i64 * iq.im as i64) as f32).sqrt()
+}
+
+/// Phase of a demodulated `I`/`Q` sample, in radians.
+pub fn phase(iq: Complex<i32>) -> f32 {
+    (iq.im as f32).atan2(iq.re as f32)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Lockin, Vec5};
+
+    #[test]
+    fn default_is_not_mute() {
+        let lockin = Lockin::default();
+        let mut state = [Vec5::default(); 2];
+        let iq = lockin.update(&mut state, 1 << 16, 1 << 28);
+        assert!(iq.re != 0 || iq.im != 0);
+    }
+}
@@ -64,6 +64,122 @@ impl PLLState {
     }
 }
 
+/// Type-III, sampled phase, discrete time PLL
+///
+/// This extends [`PLLState`] with a third (chirp/frequency-ramp) accumulator `g` below
+/// `f`, giving the loop an I^3,I^2,I transfer function from input phase to output phase
+/// (P,I from input phase to output frequency, and an additional I from input phase to
+/// output chirp rate). Against a linearly chirping input (constant frequency ramp), both
+/// this loop and `PLLState` settle to zero steady-state frequency error and a constant
+/// phase error; here `g` settles to the chirp rate and the constant phase offset is much
+/// smaller than `PLLState`'s at the same `shift`, but it is not driven to zero.
+///
+/// The third integrator's gain is half the frequency integrator's, which keeps the loop
+/// stable over the same gain range as `PLLState` without needing `shift`-dependent
+/// (e.g. quadratic) gain scaling that would otherwise blow the shift amount out past the
+/// word size for large `shift`.
+///
+/// Same wrapping 32 bit arithmetic, half-up rounding bias, and lock-to-any-alias
+/// properties as `PLLState`.
+#[derive(Copy, Clone, Default, Deserialize, Serialize)]
+pub struct PLLState3 {
+    // last input phase
+    x: i32,
+    // filtered chirp (frequency ramp)
+    g: i32,
+    // filtered frequency
+    f: i32,
+    // filtered output phase
+    y: i32,
+}
+
+impl PLLState3 {
+    /// Update the PLL with a new phase sample.
+    ///
+    /// Args:
+    /// * `input`: New input phase sample.
+    /// * `shift`: Error scaling, as in [`PLLState::update`]. Valid range is `2..=30` (the
+    ///   third integrator needs one more bit of headroom on each side than `PLLState`).
+    ///
+    /// Returns:
+    /// A tuple of instantaneous phase, frequency, and chirp rate (the current frequency
+    /// increment).
+    pub fn update(&mut self, x: i32, shift: u8) -> (i32, i32, i32) {
+        debug_assert!(shift >= 2 && shift <= 30);
+        let bias = 1i32 << shift;
+        let e = x.wrapping_sub(self.f);
+        self.g = self
+            .g
+            .wrapping_add(bias.wrapping_add(e).wrapping_sub(self.x) >> (shift + 1));
+        self.f = self.f.wrapping_add(self.g).wrapping_add(
+            (bias >> 1).wrapping_add(e).wrapping_sub(self.x) >> shift,
+        );
+        self.x = x;
+        let f = self
+            .f
+            .wrapping_add(bias.wrapping_add(e).wrapping_sub(self.y) >> (shift - 1));
+        self.y = self.y.wrapping_add(f);
+        (self.y, f, self.g)
+    }
+}
+
+/// `i64` variant of [`PLLState`], for extremely narrowband applications that need more
+/// than 32 bits of phase/frequency resolution.
+#[derive(Copy, Clone, Default, Deserialize, Serialize)]
+pub struct PLLStateI64 {
+    x: i64,
+    f: i64,
+    y: i64,
+}
+
+impl PLLStateI64 {
+    /// See [`PLLState::update`]. `shift` is valid over `1..=62`.
+    pub fn update(&mut self, x: i64, shift: u8) -> (i64, i64) {
+        debug_assert!(shift >= 1 && shift <= 62);
+        let bias = 1i64 << shift;
+        let e = x.wrapping_sub(self.f);
+        self.f = self.f.wrapping_add(
+            (bias >> 1).wrapping_add(e).wrapping_sub(self.x) >> shift,
+        );
+        self.x = x;
+        let f = self.f.wrapping_add(
+            bias.wrapping_add(e).wrapping_sub(self.y) >> shift - 1,
+        );
+        self.y = self.y.wrapping_add(f);
+        (self.y, f)
+    }
+}
+
+/// `i64` variant of [`PLLState3`], for extremely narrowband chirp tracking.
+#[derive(Copy, Clone, Default, Deserialize, Serialize)]
+pub struct PLLState3I64 {
+    x: i64,
+    g: i64,
+    f: i64,
+    y: i64,
+}
+
+impl PLLState3I64 {
+    /// See [`PLLState3::update`]. `shift` is valid over `2..=61`.
+    pub fn update(&mut self, x: i64, shift: u8) -> (i64, i64, i64) {
+        debug_assert!(shift >= 2 && shift <= 61);
+        let bias = 1i64 << shift;
+        let e = x.wrapping_sub(self.f);
+        self.g = self
+            .g
+            .wrapping_add(bias.wrapping_add(e).wrapping_sub(self.x) >> (shift + 1));
+        self.f = self.f.wrapping_add(self.g).wrapping_add(
+            (bias >> 1).wrapping_add(e).wrapping_sub(self.x) >> shift,
+        );
+        self.x = x;
+        let f = self
+            .f
+            .wrapping_add(bias.wrapping_add(e).wrapping_sub(self.y) >> (shift - 1));
+        self.y = self.y.wrapping_add(f);
+        (self.y, f, self.g)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -74,4 +190,21 @@ mod tests {
         assert_eq!(y, 0xc2);
         assert_eq!(f, y);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn mini3() {
+        let mut p = PLLState3::default();
+        let (y, f, g) = p.update(0x10000, 10);
+        assert_eq!(y, 0xe2);
+        assert_eq!(f, y);
+        assert_eq!(g, 0x20);
+    }
+
+    #[test]
+    fn mini_i64() {
+        let mut p = PLLStateI64::default();
+        let (y, f) = p.update(0x10000, 10);
+        assert_eq!(y, 0xc2);
+        assert_eq!(f, y);
+    }
+}
@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+
+use super::pll::PLLState;
+
+/// Converts a sparse stream of reference edge timestamps (captured by the sampling
+/// timer's input capture) into the dense `(phase, frequency)` pair that
+/// `PLLState::update` consumes, for locking to an external reference supplied as
+/// hardware timestamps rather than per-sample phase.
+///
+/// Each processing batch supplies the timer count of the most recently captured
+/// reference edge, how many edges were captured during the batch, and the batch's
+/// known duration in timer ticks. The reference period is estimated by dividing the
+/// batch duration by the edge count (rounded half up), so its resolution is limited by
+/// the integer number of edges captured per batch.
+///
+/// The period is then turned into a 32 bit NCO tuning word (`2^32 / period`), and the
+/// reference's absolute phase at the most recent edge is synthesized by direct digital
+/// synthesis: `timestamp.wrapping_mul(tuning_word)`. Because this only depends on the
+/// current timer count (not on a running accumulator carried across batches), timer
+/// wraparound between batches is transparent. The synthesized phase is finally smoothed
+/// by a `PLLState`, which rejects the jitter of the reciprocal-counted period estimate
+/// the same way it would reject noise on a dense per-sample phase input.
+#[derive(Copy, Clone, Deserialize, Serialize)]
+pub struct TimestampHandler {
+    pll: PLLState,
+    pll_shift: u8,
+    // Ticks-per-period of the reference, held across batches with no edges.
+    period: u32,
+}
+
+impl Default for TimestampHandler {
+    /// A derived `Default` would leave `pll_shift` and `period` at `0`, which
+    /// `update` (and `PLLState::update`'s own `debug_assert`) reject. Pick the
+    /// smallest valid values instead, matching `new`'s invariants.
+    fn default() -> Self {
+        Self {
+            pll: PLLState::default(),
+            pll_shift: 1,
+            period: 1,
+        }
+    }
+}
+
+impl TimestampHandler {
+    /// Construct a new timestamp handler.
+    ///
+    /// # Args
+    /// * `pll_shift` - `PLLState` smoothing gain. See `PLLState::update`.
+    /// * `period` - Nominal reference period, in timer ticks, used until the first
+    ///   batch with at least one edge refines the estimate. Must be nonzero.
+    pub fn new(pll_shift: u8, period: u32) -> Self {
+        debug_assert!(period > 0);
+        Self {
+            pll: PLLState::default(),
+            pll_shift,
+            period,
+        }
+    }
+
+    /// Process one batch of reference edges.
+    ///
+    /// # Args
+    /// * `timestamp` - Timer count at which the most recent reference edge in this
+    ///   batch occurred (wrapping).
+    /// * `edge_count` - Number of reference edges captured during this batch. Zero is
+    ///   handled by holding the last known period (and therefore frequency).
+    /// * `batch_period` - Duration of this batch, in timer ticks.
+    ///
+    /// # Returns
+    /// The smoothed `(phase, frequency)` of the reference, as a 32 bit NCO phase and
+    /// its increment per call of `update` (per batch). Downstream code derives the per-sample phase the
+    /// lock-in chain needs from this pair, analogous to `PLLState::update`'s output.
+    pub fn update(
+        &mut self,
+        timestamp: u32,
+        edge_count: u32,
+        batch_period: u32,
+    ) -> (i32, i32) {
+        if edge_count > 0 {
+            // Reciprocal counting: round-half-up average ticks between edges.
+            // Clamped to 1: a short batch with many edges can round this to 0,
+            // which would divide-by-zero below.
+            self.period = ((batch_period + edge_count / 2) / edge_count).max(1);
+        }
+        // For `period == 1` the true quotient is `1 << 32`, one past `u32::MAX`;
+        // saturate instead of letting the cast below silently wrap it to `0`.
+        let tuning_word = ((((1u64 << 32) + self.period as u64 / 2)
+            / self.period as u64)
+            .min(u32::MAX as u64)) as u32;
+        let phase = timestamp.wrapping_mul(tuning_word) as i32;
+        self.pll.update(phase, self.pll_shift)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::TimestampHandler;
+
+    #[test]
+    fn period_rounds_to_zero_does_not_panic() {
+        // batch_period=1, edge_count=1000: the raw reciprocal-counting division
+        // rounds to 0, which must be clamped before it reaches the reciprocal.
+        let mut h = TimestampHandler::new(4, 100);
+        h.update(0, 1000, 1);
+    }
+
+    #[test]
+    fn period_one_does_not_truncate_tuning_word_to_zero() {
+        // The tuning word saturates to u32::MAX, so the synthesized phase is
+        // -timestamp. A tuning word truncated to 0 would give phase 0 and f = 2.
+        let mut h = TimestampHandler::new(4, 1);
+        let (_, f) = h.update(1 << 20, 0, 0);
+        assert_eq!(f, -196606);
+    }
+}
@@ -59,6 +59,7 @@ macro_rules! dac_output {
                 &'static mut SampleBuffer,
             >,
             first_transfer: bool,
+            overrun_count: u32,
         }
 
         impl $name {
@@ -112,23 +113,53 @@ macro_rules! dac_output {
                     transfer,
                     // Note(unsafe): This buffer is only used once and provided for the next DMA transfer.
                     first_transfer: true,
+                    overrun_count: 0,
                 }
             }
 
             /// Acquire the next output buffer to populate it with DAC codes.
-            pub fn process<F>(&mut self, f: F)
+            ///
+            /// # Returns
+            /// True if the deadline for the previous transfer was missed (its
+            /// `get_transfer_complete_flag()` was not yet set) and a stale ping-pong
+            /// buffer was about to be emitted instead of being detected. The caller
+            /// should escalate this to a panic, matching the "DMA overflows are
+            /// signaled as panics" behavior used on the ADC side. The condition is
+            /// also latched; see `overrun_count()`.
+            ///
+            /// Note: `spi.listen(Event::Error)` is armed at construction, but nothing
+            /// here reads the SPI status register back, so a SPI-side error (as
+            /// opposed to a missed DMA deadline) is not yet detected or counted. Doing
+            /// so needs the exact status register field names for the pinned HAL
+            /// version, which isn't available in this tree; the `overrun_count` below
+            /// only reflects missed DMA transfer-complete deadlines.
+            pub fn process<F>(&mut self, f: F) -> bool
             where
                 F: FnOnce(
                     &'static mut SampleBuffer,
                 ) -> &'static mut SampleBuffer,
             {
-                // if self.first_transfer {
-                //     self.first_transfer = false
-                // } else {
-                //     while !self.transfer.get_transfer_complete_flag() {}
-                // }
-                // self.transfer.clear_interrupts();
+                let missed_deadline = if self.first_transfer {
+                    self.first_transfer = false;
+                    false
+                } else {
+                    !self.transfer.get_transfer_complete_flag()
+                };
+
+                if missed_deadline {
+                    self.overrun_count += 1;
+                }
+
+                self.transfer.clear_interrupts();
                 unsafe { self.transfer.next_transfer_with(|b, _| (f(b), ())) };
+
+                missed_deadline
+            }
+
+            /// The number of DMA transfer-complete deadlines missed so far, for the
+            /// networking/telemetry layer to publish.
+            pub fn overrun_count(&self) -> u32 {
+                self.overrun_count
             }
         }
     };